@@ -0,0 +1,845 @@
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::{AnimationDecoder, Delay, Frame, Rgb, RgbImage, Pixel, imageops};
+use palette::{Lab, LinSrgb, Srgb, FromColor, white_point::D65, Hsl, RgbHue};
+use palette::color_difference::DeltaE;
+use rand::Rng;
+use std::fs::File;
+use std::io::BufReader;
+
+#[derive(Clone, Copy)]
+pub enum Style {
+    Gruvbox,
+    Retro,
+    Synthwave,
+    Mosaic(u32),
+    Watercolor,
+    Quantize,
+    /// Flood-fills pixels into coarse-Lab-bin regions, snaps each region's
+    /// mean color to the palette once, and recolors the whole region toward
+    /// that single target instead of mapping pixel-by-pixel.
+    Segmented { min_region: u32, clusters: u32 },
+}
+
+const GRUVBOX_LAB: [Lab<D65>; 9] = [
+    Lab::new(29.77, 0.16, 0.20),     // background
+    Lab::new(86.97, -0.86, 9.92),    // foreground
+    Lab::new(44.36, 55.40, 37.13),   // red
+    Lab::new(56.83, -21.99, 56.27),  // green
+    Lab::new(65.17, 10.15, 57.42),   // yellow
+    Lab::new(49.59, -9.26, -24.91),  // blue
+    Lab::new(51.70, 34.04, -14.60),  // purple
+    Lab::new(58.69, -28.30, 15.25),  // aqua
+    Lab::new(53.33, 39.77, 52.78),   // orange
+];
+
+const NORD_HEX: [&str; 9] = [
+    "#2E3440", "#D8DEE9", "#BF616A", "#A3BE8C", "#EBCB8B", "#81A1C1", "#B48EAD", "#88C0D0",
+    "#D08770",
+];
+
+const DRACULA_HEX: [&str; 9] = [
+    "#282A36", "#F8F8F2", "#FF5555", "#50FA7B", "#F1FA8C", "#6272A4", "#BD93F9", "#8BE9FD",
+    "#FFB86C",
+];
+
+const SOLARIZED_HEX: [&str; 9] = [
+    "#002B36", "#FDF6E3", "#DC322F", "#859900", "#B58900", "#268BD2", "#D33682", "#2AA198",
+    "#CB4B16",
+];
+
+/// A set of reference colors an image is harmonized toward. Defaults to
+/// Gruvbox, but can be swapped for another built-in theme or a user-supplied
+/// `--palette` file, so the same harmonization/quantization code works for
+/// any retheme.
+pub type Palette = Vec<Lab<D65>>;
+
+pub fn gruvbox_palette() -> Palette {
+    GRUVBOX_LAB.to_vec()
+}
+
+/// Parses a single `#rrggbb` hex color into Lab via the existing Srgb→Lab
+/// path used everywhere else in the crate.
+pub fn hex_to_lab(hex: &str) -> Result<Lab<D65>, Box<dyn std::error::Error>> {
+    let hex = hex.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return Err(format!("invalid hex color: #{hex}").into());
+    }
+    let r = u8::from_str_radix(&hex[0..2], 16)?;
+    let g = u8::from_str_radix(&hex[2..4], 16)?;
+    let b = u8::from_str_radix(&hex[4..6], 16)?;
+    let srgb: Srgb<f32> = Srgb::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    Ok(Lab::from_color(srgb))
+}
+
+/// Looks up a built-in theme by name: `gruvbox`, `nord`, `dracula`, or
+/// `solarized`.
+pub fn theme_palette(name: &str) -> Option<Result<Palette, Box<dyn std::error::Error>>> {
+    let hexes: &[&str] = match name {
+        "gruvbox" => return Some(Ok(gruvbox_palette())),
+        "nord" => &NORD_HEX,
+        "dracula" => &DRACULA_HEX,
+        "solarized" => &SOLARIZED_HEX,
+        _ => return None,
+    };
+    Some(hexes.iter().map(|h| hex_to_lab(h)).collect())
+}
+
+/// Loads a palette file: one `#rrggbb` hex color per line.
+pub fn load_palette_file(path: &str) -> Result<Palette, Box<dyn std::error::Error>> {
+    let contents = std::fs::read_to_string(path)?;
+    let palette: Palette = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(hex_to_lab)
+        .collect::<Result<_, _>>()?;
+    if palette.is_empty() {
+        return Err(format!("palette file {path} contains no colors").into());
+    }
+    Ok(palette)
+}
+
+/// All knobs that drive a single styling run. Mirrors the shape of
+/// `sss_lib`'s `generate_image(GenerationSettings{..})`: a binary (or any
+/// other Rust caller) fills this in and hands it to [`style_image`] instead
+/// of driving the individual passes itself.
+pub struct GenerationSettings {
+    pub style: Style,
+    pub strength: f32,
+    pub palette: Palette,
+    pub contrast_boost: f32,
+    pub film_grain: i16,
+    pub dither: bool,
+    pub watercolor_turbulence: f32,
+    pub adjustments: ColorAdjustments,
+}
+
+impl Default for GenerationSettings {
+    fn default() -> Self {
+        GenerationSettings {
+            style: Style::Gruvbox,
+            strength: 1.0,
+            palette: gruvbox_palette(),
+            contrast_boost: 1.08,
+            film_grain: 12,
+            dither: false,
+            watercolor_turbulence: 8.0,
+            adjustments: ColorAdjustments::default(),
+        }
+    }
+}
+
+/// Tone correction applied ahead of harmonization, so users can pre-grade
+/// the input (e.g. desaturate and raise gamma for a muted matte look)
+/// instead of being stuck with whatever `enhanced_harmonization` bakes in.
+/// `brightness` is additive, `contrast`/`saturation`/`gamma` are
+/// multiplicative/exponential around their neutral value of 1.0, and `hue`
+/// is a shift in degrees.
+#[derive(Clone, Copy)]
+pub struct ColorAdjustments {
+    pub brightness: f32,
+    pub contrast: f32,
+    pub saturation: f32,
+    pub hue: f32,
+    pub gamma: f32,
+}
+
+impl Default for ColorAdjustments {
+    fn default() -> Self {
+        ColorAdjustments {
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            hue: 0.0,
+            gamma: 1.0,
+        }
+    }
+}
+
+impl ColorAdjustments {
+    fn is_neutral(&self) -> bool {
+        self.brightness == 0.0
+            && self.contrast == 1.0
+            && self.saturation == 1.0
+            && self.hue == 0.0
+            && self.gamma == 1.0
+    }
+}
+
+/// Applies hue shift, HSL saturation and brightness, gamma, and contrast, in
+/// that order, to every pixel.
+fn apply_color_adjustments(img: &mut RgbImage, adjustments: &ColorAdjustments) {
+    if adjustments.is_neutral() {
+        return;
+    }
+
+    for pixel in img.pixels_mut() {
+        let rgb = pixel.to_rgb();
+        let srgb = Srgb::new(
+            rgb[0] as f32 / 255.0,
+            rgb[1] as f32 / 255.0,
+            rgb[2] as f32 / 255.0,
+        );
+
+        let mut hsl = Hsl::from_color(srgb);
+        hsl.hue += RgbHue::from_degrees(adjustments.hue);
+        hsl.saturation = (hsl.saturation * adjustments.saturation).clamp(0.0, 1.0);
+        hsl.lightness = (hsl.lightness + adjustments.brightness).clamp(0.0, 1.0);
+
+        let linear: LinSrgb<f32> = Srgb::from_color(hsl).into_linear();
+        let gamma = 1.0 / adjustments.gamma;
+        let gamma_corrected = LinSrgb::new(
+            linear.red.max(0.0).powf(gamma),
+            linear.green.max(0.0).powf(gamma),
+            linear.blue.max(0.0).powf(gamma),
+        );
+
+        let toned = Srgb::from_linear(gamma_corrected);
+        let mut channels = [toned.red, toned.green, toned.blue];
+        for v in &mut channels {
+            *v = ((*v - 0.5) * adjustments.contrast + 0.5).clamp(0.0, 1.0);
+        }
+
+        pixel[0] = (channels[0] * 255.0) as u8;
+        pixel[1] = (channels[1] * 255.0) as u8;
+        pixel[2] = (channels[2] * 255.0) as u8;
+    }
+}
+
+/// Runs `settings.style` over `img` in place, then quantizes the result onto
+/// `settings.palette` with Floyd-Steinberg dithering if `settings.dither` is
+/// set.
+pub fn style_image(img: &mut RgbImage, settings: &GenerationSettings) {
+    apply_style(
+        img,
+        settings.style,
+        settings.strength,
+        &settings.palette,
+        settings.contrast_boost,
+        settings.film_grain,
+        settings.watercolor_turbulence,
+        &settings.adjustments,
+    );
+    if settings.dither {
+        quantize_dither(img, &settings.palette);
+    }
+}
+
+/// Runs the color adjustments and a single `style` pass over `img` in place,
+/// without the palette quantization/dithering [`style_image`] adds on top.
+/// Exposed for callers that want to drive individual passes directly instead
+/// of going through a full [`GenerationSettings`].
+pub fn apply_style(
+    img: &mut RgbImage,
+    style: Style,
+    strength: f32,
+    palette: &Palette,
+    contrast_boost: f32,
+    film_grain: i16,
+    watercolor_turbulence: f32,
+    adjustments: &ColorAdjustments,
+) {
+    apply_color_adjustments(img, adjustments);
+
+    match style {
+        Style::Gruvbox => {
+            let gray = imageops::colorops::grayscale(img);
+            let filtered = imageproc::filter::bilateral_filter(&gray, 5, 25.0, 2.0);
+            enhanced_harmonization(img, strength, Some(&filtered), palette, contrast_boost);
+        }
+        Style::Retro => {
+            let gray = imageops::colorops::grayscale(img);
+            let filtered = imageproc::filter::bilateral_filter(&gray, 3, 15.0, 1.5);
+            enhanced_harmonization(img, strength, Some(&filtered), palette, contrast_boost);
+            add_vhs_effect(img);
+            add_film_grain(img, film_grain);
+        }
+        Style::Synthwave => {
+            enhanced_harmonization(img, strength * 0.8, None, palette, contrast_boost);
+            apply_gradient_overlay(img);
+            boost_saturation(img, 1.5);
+        }
+        Style::Mosaic(size) => {
+            enhanced_harmonization(img, strength, None, palette, contrast_boost);
+            pixelate(img, size);
+        }
+        Style::Watercolor => {
+            enhanced_harmonization(img, strength, None, palette, contrast_boost);
+            apply_watercolor_effect(img, watercolor_turbulence);
+        }
+        Style::Quantize => {
+            quantize_dither(img, palette);
+        }
+        Style::Segmented { min_region, clusters } => {
+            apply_segmented(img, strength, palette, min_region, clusters);
+        }
+    }
+}
+
+fn enhanced_harmonization(
+    img: &mut RgbImage,
+    strength: f32,
+    edge_mask: Option<&image::GrayImage>,
+    palette: &Palette,
+    contrast_boost: f32,
+) {
+    let strength = strength.clamp(0.0, 1.0);
+
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        let rgb = pixel.to_rgb();
+        let srgb = Srgb::new(
+            rgb[0] as f32 / 255.0,
+            rgb[1] as f32 / 255.0,
+            rgb[2] as f32 / 255.0
+        ).into_format();
+
+        let original_lab: Lab<D65> = Lab::from_color(srgb);
+        let mut harmonized = harmonize_color(original_lab, strength, palette);
+
+        // Edge-aware strength adjustment
+        if let Some(mask) = edge_mask {
+            let edge_strength = mask.get_pixel(x, y)[0] as f32 / 255.0;
+            harmonized.l = original_lab.l * (1.0 - edge_strength) + harmonized.l * edge_strength;
+        }
+
+        let mut result_rgb = Srgb::from_color(harmonized).into_format::<f32>();
+
+        // Contrast compensation
+        result_rgb.red = (result_rgb.red * contrast_boost).clamp(0.0, 1.0);
+        result_rgb.green = (result_rgb.green * contrast_boost).clamp(0.0, 1.0);
+        result_rgb.blue = (result_rgb.blue * contrast_boost).clamp(0.0, 1.0);
+
+        pixel[0] = (result_rgb.red * 255.0) as u8;
+        pixel[1] = (result_rgb.green * 255.0) as u8;
+        pixel[2] = (result_rgb.blue * 255.0) as u8;
+    }
+}
+
+fn harmonize_color(original: Lab<D65>, strength: f32, palette: &Palette) -> Lab<D65> {
+    let target = nearest_in_palette(original, palette);
+    blend_toward(original, target, strength)
+}
+
+/// Blends `original` toward `target` with a sigmoid-based mix, so nearby
+/// colors move in smoothly rather than snapping.
+fn blend_toward(original: Lab<D65>, target: Lab<D65>, strength: f32) -> Lab<D65> {
+    fn blend_channel(orig: f32, tgt: f32, strength: f32) -> f32 {
+        let mix = strength * (1.0 - (-4.0 * (orig - tgt).abs()).exp()).recip();
+        orig * (1.0 - mix) + tgt * mix
+    }
+
+    Lab::new(
+        original.l * 0.98 + target.l * 0.02,
+        blend_channel(original.a, target.a, strength),
+        blend_channel(original.b, target.b, strength),
+    )
+}
+
+/// Finds the palette entry nearest `lab` in CIE76 delta-E.
+fn nearest_in_palette(lab: Lab<D65>, palette: &Palette) -> Lab<D65> {
+    *palette
+        .iter()
+        .min_by_key(|&&c| (lab.delta_e(c) * 1000.0) as u32)
+        .unwrap()
+}
+
+/// Maps every pixel strictly onto `palette` with Floyd-Steinberg error
+/// diffusion, giving a crisp posterized result instead of the soft blend
+/// `harmonize_color` produces.
+fn quantize_dither(img: &mut RgbImage, palette: &Palette) {
+    let (width, height) = img.dimensions();
+    let mut row: Vec<[f32; 3]> = vec![[0.0; 3]; width as usize];
+    let mut next_row: Vec<[f32; 3]> = vec![[0.0; 3]; width as usize];
+
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = img.get_pixel(x, y).to_rgb();
+            let x = x as usize;
+
+            let original = [
+                pixel[0] as f32 / 255.0 + row[x][0],
+                pixel[1] as f32 / 255.0 + row[x][1],
+                pixel[2] as f32 / 255.0 + row[x][2],
+            ];
+
+            let srgb = Srgb::new(
+                original[0].clamp(0.0, 1.0),
+                original[1].clamp(0.0, 1.0),
+                original[2].clamp(0.0, 1.0),
+            );
+            let lab: Lab<D65> = Lab::from_color(srgb);
+            let chosen_lab = nearest_in_palette(lab, palette);
+            let chosen_rgb = Srgb::from_color(chosen_lab);
+            let chosen = [chosen_rgb.red, chosen_rgb.green, chosen_rgb.blue];
+
+            let error = [
+                original[0] - chosen[0],
+                original[1] - chosen[1],
+                original[2] - chosen[2],
+            ];
+
+            let last_col = x + 1 == width as usize;
+            let first_col = x == 0;
+
+            for c in 0..3 {
+                if !last_col {
+                    row[x + 1][c] += error[c] * 7.0 / 16.0;
+                }
+                if y + 1 < height {
+                    if !first_col {
+                        next_row[x - 1][c] += error[c] * 3.0 / 16.0;
+                    }
+                    next_row[x][c] += error[c] * 5.0 / 16.0;
+                    if !last_col {
+                        next_row[x + 1][c] += error[c] * 1.0 / 16.0;
+                    }
+                }
+            }
+
+            let out = img.get_pixel_mut(x as u32, y);
+            out[0] = (chosen[0].clamp(0.0, 1.0) * 255.0) as u8;
+            out[1] = (chosen[1].clamp(0.0, 1.0) * 255.0) as u8;
+            out[2] = (chosen[2].clamp(0.0, 1.0) * 255.0) as u8;
+        }
+
+        row = std::mem::replace(&mut next_row, vec![[0.0; 3]; width as usize]);
+    }
+}
+
+/// Groups pixels into coherent regions, snaps each region's mean color to
+/// the palette once, and recolors the whole region toward that one target
+/// with [`blend_toward`], so smooth gradients come out as flat poster-like
+/// areas with clean boundaries instead of speckled per-pixel mapping.
+///
+/// Regions are found by quantizing each pixel's Lab color into coarse bins
+/// (`clusters` controls how fine the bins are) and flood-filling 4-connected
+/// runs of the same bin. Regions smaller than `min_region` pixels are left
+/// to ordinary per-pixel harmonization, since a single-target blend isn't
+/// meaningful for a handful of stray pixels.
+fn apply_segmented(img: &mut RgbImage, strength: f32, palette: &Palette, min_region: u32, clusters: u32) {
+    let (width, height) = img.dimensions();
+    let (w, h) = (width as usize, height as usize);
+
+    let labs: Vec<Lab<D65>> = img
+        .pixels()
+        .map(|p| {
+            let rgb = p.to_rgb();
+            let srgb = Srgb::new(
+                rgb[0] as f32 / 255.0,
+                rgb[1] as f32 / 255.0,
+                rgb[2] as f32 / 255.0,
+            );
+            Lab::from_color(srgb)
+        })
+        .collect();
+
+    // Coarser bins (fewer, larger) with fewer clusters; finer bins with more.
+    let bin_width = 100.0 / clusters.max(1) as f32;
+    let bin_key = |lab: Lab<D65>| -> (i32, i32, i32) {
+        (
+            (lab.l / bin_width).floor() as i32,
+            (lab.a / bin_width).floor() as i32,
+            (lab.b / bin_width).floor() as i32,
+        )
+    };
+    let keys: Vec<(i32, i32, i32)> = labs.iter().map(|&lab| bin_key(lab)).collect();
+
+    let mut region_of = vec![usize::MAX; w * h];
+    let mut regions: Vec<Vec<usize>> = Vec::new();
+
+    for start in 0..w * h {
+        if region_of[start] != usize::MAX {
+            continue;
+        }
+        let key = keys[start];
+        let region_idx = regions.len();
+        region_of[start] = region_idx;
+        let mut members = vec![start];
+        let mut stack = vec![start];
+
+        while let Some(idx) = stack.pop() {
+            let (x, y) = (idx % w, idx / w);
+            let mut neighbors = Vec::with_capacity(4);
+            if x > 0 {
+                neighbors.push(idx - 1);
+            }
+            if x + 1 < w {
+                neighbors.push(idx + 1);
+            }
+            if y > 0 {
+                neighbors.push(idx - w);
+            }
+            if y + 1 < h {
+                neighbors.push(idx + w);
+            }
+            for neighbor in neighbors {
+                if region_of[neighbor] == usize::MAX && keys[neighbor] == key {
+                    region_of[neighbor] = region_idx;
+                    members.push(neighbor);
+                    stack.push(neighbor);
+                }
+            }
+        }
+
+        regions.push(members);
+    }
+
+    for members in &regions {
+        if members.len() as u32 >= min_region {
+            let mut sum = Lab::new(0.0, 0.0, 0.0);
+            for &idx in members {
+                let lab = labs[idx];
+                sum = Lab::new(sum.l + lab.l, sum.a + lab.a, sum.b + lab.b);
+            }
+            let n = members.len() as f32;
+            let mean = Lab::new(sum.l / n, sum.a / n, sum.b / n);
+            let target = nearest_in_palette(mean, palette);
+
+            for &idx in members {
+                let blended = blend_toward(labs[idx], target, strength);
+                write_lab(img, (idx % w) as u32, (idx / w) as u32, blended);
+            }
+        } else {
+            for &idx in members {
+                let blended = harmonize_color(labs[idx], strength, palette);
+                write_lab(img, (idx % w) as u32, (idx / w) as u32, blended);
+            }
+        }
+    }
+}
+
+fn write_lab(img: &mut RgbImage, x: u32, y: u32, lab: Lab<D65>) {
+    let rgb = Srgb::from_color(lab).into_format::<f32>();
+    let pixel = img.get_pixel_mut(x, y);
+    pixel[0] = (rgb.red.clamp(0.0, 1.0) * 255.0) as u8;
+    pixel[1] = (rgb.green.clamp(0.0, 1.0) * 255.0) as u8;
+    pixel[2] = (rgb.blue.clamp(0.0, 1.0) * 255.0) as u8;
+}
+
+fn add_vhs_effect(img: &mut RgbImage) {
+    let (width, height) = img.dimensions();
+    let mut shifted_r = img.clone();
+    let mut shifted_b = img.clone();
+
+    shifted_r = imageops::crop(&mut shifted_r, 2, 0, width-2, height).to_image();
+    shifted_b = imageops::crop(&mut shifted_b, 0, 1, width, height-1).to_image();
+
+    imageops::overlay(img, &shifted_r, 0, 0);
+    imageops::overlay(img, &shifted_b, 0, 0);
+
+    for (_, y, pixel) in img.enumerate_pixels_mut() {
+        if y % 2 == 0 {
+            pixel[0] = pixel[0].saturating_sub(20);
+            pixel[1] = pixel[1].saturating_sub(20);
+            pixel[2] = pixel[2].saturating_sub(20);
+        }
+    }
+}
+
+fn add_film_grain(img: &mut RgbImage, intensity: i16) {
+    let mut rng = rand::thread_rng();
+    for pixel in img.pixels_mut() {
+        let noise = rng.gen_range(-intensity..intensity);
+        pixel[0] = (pixel[0] as i16 + noise).clamp(0, 255) as u8;
+        pixel[1] = (pixel[1] as i16 + noise).clamp(0, 255) as u8;
+        pixel[2] = (pixel[2] as i16 + noise).clamp(0, 255) as u8;
+    }
+}
+
+fn apply_gradient_overlay(img: &mut RgbImage) {
+    let height = img.height() as f32;
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        let position = y as f32 / height;
+        let r = (position * 255.0) as u8;
+        let b = ((1.0 - position) * 255.0) as u8;
+        pixel[0] = pixel[0].saturating_add(r / 2);
+        pixel[2] = pixel[2].saturating_add(b / 2);
+    }
+}
+
+fn boost_saturation(img: &mut RgbImage, factor: f32) {
+    for pixel in img.pixels_mut() {
+        let rgb = pixel.to_rgb();
+        let mut hsl = Hsl::from_color(Srgb::new(
+            rgb[0] as f32 / 255.0,
+            rgb[1] as f32 / 255.0,
+            rgb[2] as f32 / 255.0
+        ));
+
+        hsl.saturation *= factor;
+        let srgb = Srgb::from_color(hsl);
+
+        pixel[0] = (srgb.red * 255.0).clamp(0.0, 255.0) as u8;
+        pixel[1] = (srgb.green * 255.0).clamp(0.0, 255.0) as u8;
+        pixel[2] = (srgb.blue * 255.0).clamp(0.0, 255.0) as u8;
+    }
+}
+
+fn pixelate(img: &mut RgbImage, block_size: u32) {
+    let (w, h) = img.dimensions();
+    let small = imageops::resize(
+        &*img,
+        w / block_size,
+        h / block_size,
+        imageops::FilterType::Nearest,
+    );
+    *img = imageops::resize(&small, w, h, imageops::FilterType::Nearest);
+}
+
+/// Fixed seed for the watercolor turbulence field, so a given image always
+/// bleeds the same way instead of re-rolling on every run.
+const WATERCOLOR_SEED: u32 = 1337;
+const WATERCOLOR_OCTAVES: u32 = 4;
+
+/// Hash-based value noise, smoothed with a bilinear Hermite blend between
+/// lattice corners. Cheap stand-in for Perlin noise that needs no extra
+/// dependency and is deterministic for a given `seed`.
+fn value_noise(x: f32, y: f32, seed: u32) -> f32 {
+    fn hash(ix: i32, iy: i32, seed: u32) -> f32 {
+        let h = (ix as u32)
+            .wrapping_mul(374761393)
+            ^ (iy as u32).wrapping_mul(668265263)
+            ^ seed.wrapping_mul(2246822519);
+        let h = (h ^ (h >> 13)).wrapping_mul(1274126177);
+        let h = h ^ (h >> 16);
+        (h as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let (fx, fy) = (x - x0, y - y0);
+    let (x0i, y0i) = (x0 as i32, y0 as i32);
+
+    let v00 = hash(x0i, y0i, seed);
+    let v10 = hash(x0i + 1, y0i, seed);
+    let v01 = hash(x0i, y0i + 1, seed);
+    let v11 = hash(x0i + 1, y0i + 1, seed);
+
+    let sx = fx * fx * (3.0 - 2.0 * fx);
+    let sy = fy * fy * (3.0 - 2.0 * fy);
+    let top = v00 + sx * (v10 - v00);
+    let bottom = v01 + sx * (v11 - v01);
+    top + sy * (bottom - top)
+}
+
+/// Sums `WATERCOLOR_OCTAVES` octaves of value noise at decreasing amplitude
+/// and doubling frequency, i.e. fractal turbulence.
+fn turbulence(x: f32, y: f32, seed: u32) -> f32 {
+    let mut sum = 0.0;
+    let mut freq = 1.0;
+    let mut amp = 1.0;
+    for octave in 0..WATERCOLOR_OCTAVES {
+        sum += value_noise(x * freq, y * freq, seed.wrapping_add(octave)) * amp;
+        freq *= 2.0;
+        amp *= 0.5;
+    }
+    sum
+}
+
+/// Displaces each output pixel along a turbulence field before sampling the
+/// blurred image, and blends in a heavier blur where the turbulence is
+/// strongest, so the bleed looks like pigment spreading into wet paper
+/// rather than uniform per-pixel noise.
+fn apply_watercolor_effect(img: &mut RgbImage, intensity: f32) {
+    let (width, height) = img.dimensions();
+    let soft = imageops::blur(img, 2.0);
+    let softer = imageops::blur(img, 5.0);
+
+    const FIELD_SCALE: f32 = 0.02;
+
+    for y in 0..height {
+        for x in 0..width {
+            let (fx, fy) = (x as f32, y as f32);
+            let dx = turbulence(fx * FIELD_SCALE, fy * FIELD_SCALE, WATERCOLOR_SEED) * intensity;
+            let dy = turbulence(
+                fx * FIELD_SCALE + 100.0,
+                fy * FIELD_SCALE + 100.0,
+                WATERCOLOR_SEED.wrapping_add(1),
+            ) * intensity;
+
+            let sample_x = (fx + dx).round().clamp(0.0, width as f32 - 1.0) as u32;
+            let sample_y = (fy + dy).round().clamp(0.0, height as f32 - 1.0) as u32;
+
+            let bleed = ((dx * dx + dy * dy).sqrt() / intensity.max(0.001)).clamp(0.0, 1.0);
+            let soft_pixel = soft.get_pixel(sample_x, sample_y);
+            let softer_pixel = softer.get_pixel(sample_x, sample_y);
+
+            let pixel = img.get_pixel_mut(x, y);
+            for c in 0..3 {
+                let mixed =
+                    soft_pixel[c] as f32 * (1.0 - bleed) + softer_pixel[c] as f32 * bleed;
+                pixel[c] = mixed.clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+}
+
+/// Number of trailing styled frames tracked per pixel when suppressing
+/// flicker in animated output.
+const LOOKAHEAD: usize = 5;
+
+/// Per-pixel history used by the temporal denoiser: the last `LOOKAHEAD`
+/// styled values seen at this position, the value currently frozen for
+/// output, how many consecutive frames it's already been held for, and the
+/// most frames it's allowed to stay frozen before it must refresh.
+struct Acc {
+    values: [Rgb<u8>; LOOKAHEAD],
+    filled: usize,
+    frozen: Rgb<u8>,
+    stayed_for: u32,
+    can_stay_for: u32,
+}
+
+impl Acc {
+    fn new(initial: Rgb<u8>) -> Self {
+        Acc {
+            values: [initial; LOOKAHEAD],
+            filled: 0,
+            frozen: initial,
+            stayed_for: 0,
+            can_stay_for: LOOKAHEAD as u32,
+        }
+    }
+
+    /// Pushes a newly styled pixel into the window. Returns the value that
+    /// should be emitted for the oldest frame in the window once the window
+    /// has filled (`None` until then, which delays output by `LOOKAHEAD - 1`
+    /// frames): the already-frozen value if every sample currently in the
+    /// window stays within `threshold` of every other and it has been held
+    /// for fewer than `can_stay_for` frames, else a refreshed value taken
+    /// from the oldest sample.
+    fn push(&mut self, current: Rgb<u8>, threshold: f32) -> Option<Rgb<u8>> {
+        self.values.rotate_left(1);
+        self.values[LOOKAHEAD - 1] = current;
+        self.filled = (self.filled + 1).min(LOOKAHEAD);
+        if self.filled < LOOKAHEAD {
+            return None;
+        }
+
+        let mut max_spread = 0.0f32;
+        for i in 0..LOOKAHEAD {
+            for j in (i + 1)..LOOKAHEAD {
+                max_spread = max_spread.max(channel_distance(self.values[i], self.values[j]));
+            }
+        }
+
+        let oldest = self.values[0];
+        if max_spread <= threshold && self.stayed_for < self.can_stay_for {
+            self.stayed_for += 1;
+        } else {
+            self.stayed_for = 0;
+            self.frozen = oldest;
+        }
+        Some(self.frozen)
+    }
+}
+
+fn channel_distance(a: Rgb<u8>, b: Rgb<u8>) -> f32 {
+    let dr = a[0] as f32 - b[0] as f32;
+    let dg = a[1] as f32 - b[1] as f32;
+    let db = a[2] as f32 - b[2] as f32;
+    (dr * dr + dg * dg + db * db).sqrt()
+}
+
+pub fn is_gif(path: &str) -> bool {
+    path.to_ascii_lowercase().ends_with(".gif")
+}
+
+/// Styles every frame of an animated GIF and re-encodes it, smoothing over
+/// the shimmer that per-frame quantization/dithering/grain would otherwise
+/// introduce in regions that should stay static. Modeled on gifski's
+/// sliding-window accumulator: a pixel keeps its frozen value while every
+/// sample in its `LOOKAHEAD`-frame window stays within `stability` of every
+/// other, and refreshes from the oldest sample once the window spreads wider
+/// or the value has been held for `LOOKAHEAD` frames.
+pub fn apply_style_gif(
+    input: &str,
+    output: &str,
+    settings: &GenerationSettings,
+    stability: f32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let decoder = GifDecoder::new(BufReader::new(File::open(input)?))?;
+    let frames = decoder.into_frames().collect_frames()?;
+
+    let mut styled: Vec<(RgbImage, Delay)> = Vec::with_capacity(frames.len());
+    for frame in &frames {
+        let delay = frame.delay();
+        let mut img = image::DynamicImage::ImageRgba8(frame.buffer().clone()).into_rgb8();
+        style_image(&mut img, settings);
+        styled.push((img, delay));
+    }
+
+    let (width, height) = match styled.first() {
+        Some((img, _)) => img.dimensions(),
+        None => return Err("GIF has no frames".into()),
+    };
+
+    let mut out_frames = Vec::with_capacity(styled.len());
+
+    if styled.len() < LOOKAHEAD {
+        // Too short for the window to ever fill; pass the styled frames
+        // through unstabilized rather than emitting nothing.
+        for (img, delay) in &styled {
+            out_frames.push(Frame::from_parts(
+                image::RgbaImage::from_fn(width, height, |x, y| img.get_pixel(x, y).to_rgba()),
+                0,
+                0,
+                *delay,
+            ));
+        }
+    } else {
+        let mut accs: Vec<Acc> = styled[0].0.pixels().map(|p| Acc::new(*p)).collect();
+
+        for (i, (img, _)) in styled.iter().enumerate() {
+            let mut stabilized = RgbImage::new(width, height);
+            for (acc, (x, y, pixel)) in accs.iter_mut().zip(img.enumerate_pixels()) {
+                if let Some(emitted) = acc.push(*pixel, stability) {
+                    stabilized.put_pixel(x, y, emitted);
+                }
+            }
+
+            // The window has just filled for every pixel at once, so once
+            // `push` starts returning values it does so for the whole frame.
+            if i + 1 >= LOOKAHEAD {
+                let delay = styled[i + 1 - LOOKAHEAD].1;
+                out_frames.push(Frame::from_parts(
+                    image::RgbaImage::from_fn(width, height, |x, y| {
+                        stabilized.get_pixel(x, y).to_rgba()
+                    }),
+                    0,
+                    0,
+                    delay,
+                ));
+            }
+        }
+
+        // The main loop only emits once the window has filled, which leaves
+        // the last `LOOKAHEAD - 1` input frames still buffered. Drain the
+        // window by repeating the final frame's pixels as input (there's no
+        // newer data to feed it) so every input frame still gets an output
+        // frame, with its original delay.
+        let (last_img, _) = styled.last().expect("checked non-empty above");
+        for k in (styled.len() - LOOKAHEAD + 1)..styled.len() {
+            let delay = styled[k].1;
+            let mut stabilized = RgbImage::new(width, height);
+            for (acc, (x, y, pixel)) in accs.iter_mut().zip(last_img.enumerate_pixels()) {
+                if let Some(emitted) = acc.push(*pixel, stability) {
+                    stabilized.put_pixel(x, y, emitted);
+                }
+            }
+            out_frames.push(Frame::from_parts(
+                image::RgbaImage::from_fn(width, height, |x, y| {
+                    stabilized.get_pixel(x, y).to_rgba()
+                }),
+                0,
+                0,
+                delay,
+            ));
+        }
+    }
+
+    let mut encoder = GifEncoder::new(File::create(output)?);
+    encoder.set_repeat(Repeat::Infinite)?;
+    encoder.encode_frames(out_frames)?;
+    Ok(())
+}